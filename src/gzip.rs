@@ -1,4 +1,71 @@
-use anyhow::{self, Result, bail, ensure};
+use core::fmt;
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+
+/// Decoding/encoding errors. Carries no payload, so constructing one never
+/// allocates — this is what lets the whole module build under `no_std` +
+/// `alloc` (see the crate's `std` feature).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Input doesn't start with the gzip magic bytes, or a zlib header's
+    /// FCHECK test fails.
+    InvalidMagic,
+    /// `parse_header` was called a second time on the same `Decoder`.
+    HeaderAlreadyParsed,
+    /// The CM (compression method) header field isn't 8 (DEFLATE).
+    UnsupportedMethod,
+    /// Ran out of input before a header field, block, or symbol finished.
+    UnexpectedEof,
+    /// An FNAME/FCOMMENT field wasn't valid UTF-8.
+    InvalidUtf8,
+    /// A stored block's length and its one's-complement disagree.
+    StoredBlockLengthMismatch,
+    /// Block type bits were `0b11`, which RFC 1951 reserves.
+    ReservedBlockType,
+    /// A Huffman-coded symbol or table was malformed.
+    BadHuffman,
+    /// A checksum didn't match: the gzip CRC32/ISIZE trailer, the gzip
+    /// FHCRC header CRC, or the zlib Adler-32 trailer.
+    ChecksumMismatch,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            DecodeError::InvalidMagic => "invalid gzip magic bytes",
+            DecodeError::HeaderAlreadyParsed => "header already parsed",
+            DecodeError::UnsupportedMethod => "unsupported compression method",
+            DecodeError::UnexpectedEof => "unexpected end of input",
+            DecodeError::InvalidUtf8 => "invalid UTF-8 in string field",
+            DecodeError::StoredBlockLengthMismatch => "one's complement verification failed",
+            DecodeError::ReservedBlockType => "reserved block type",
+            DecodeError::BadHuffman => "invalid or undecodable Huffman data",
+            DecodeError::ChecksumMismatch => "checksum mismatch",
+        };
+        f.write_str(msg)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+/// Crate-wide result alias; `DecodeError` is used with or without `std`.
+pub type Result<T> = core::result::Result<T, DecodeError>;
+
+macro_rules! bail {
+    ($e:expr) => {
+        return Err($e)
+    };
+}
+
+macro_rules! ensure {
+    ($cond:expr, $e:expr) => {
+        if !($cond) {
+            return Err($e);
+        }
+    };
+}
 
 const ID1: u8 = 0x1F;
 const ID2: u8 = 0x8B;
@@ -10,6 +77,40 @@ const FEXTRA: u8 = 1 << 2;
 const FNAME: u8 = 1 << 3;
 const FCOMMENT: u8 = 1 << 4;
 
+/// Size base for length codes 257..285
+const LENGTH_BASE: [u32; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+/// Extra bits for length codes 257..285
+const LENGTH_EXTRA: [u32; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// Offset base for distance codes 0..29
+const DIST_BASE: [u32; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+/// Extra bits for distance codes 0..29
+const DIST_EXTRA: [u32; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Bit-lengths of the fixed Huffman codes defined by RFC 1951 3.2.6,
+/// shared by both the fixed-block decoder and encoder.
+fn fixed_huffman_lengths() -> ([u32; 288], [u32; 30]) {
+    let mut lengths: [u32; 288] = [0; 288];
+    lengths[..=143].fill(8);
+    lengths[144..=255].fill(9);
+    lengths[256..=279].fill(7);
+    lengths[280..=287].fill(8);
+
+    let distances: [u32; 30] = [5; 30];
+
+    (lengths, distances)
+}
+
 #[derive(Debug, Default)]
 pub struct Header {
     pub comment: String,
@@ -24,6 +125,12 @@ pub struct Decoder<'a> {
     pub header: Header,
     pub pos: usize,
     pub input_stream: &'a [u8],
+    /// Whether to check the FHCRC header checksum and the CRC32/ISIZE
+    /// trailer against the decoded bytes. On by default; a caller that
+    /// trusts its input can set this to `false`. The 8-byte trailer is
+    /// read either way, so a stream cut short before it still fails with
+    /// `UnexpectedEof`.
+    pub verify: bool,
 }
 
 impl<'a> Decoder<'a> {
@@ -32,42 +139,29 @@ impl<'a> Decoder<'a> {
             header: Header::default(),
             input_stream: input,
             pos: 0,
+            verify: true,
         }
     }
 
     pub fn parse_header(&mut self) -> Result<()> {
         if self.pos != 0 {
             // header already parsed
-            anyhow::bail!("Header already parsed");
+            bail!(DecodeError::HeaderAlreadyParsed);
         }
 
-        anyhow::ensure!(
+        ensure!(
             self.input_stream.len() >= 10,
-            "Input too short for gzip header"
+            DecodeError::UnexpectedEof
         );
 
         let id1 = self.read_byte()?;
-        ensure!(
-            id1 == ID1,
-            "Invalid gzip magic byte 1: expected {:#x}, got {:#x}",
-            ID1,
-            id1
-        );
+        ensure!(id1 == ID1, DecodeError::InvalidMagic);
 
         let id2 = self.read_byte()?;
-        ensure!(
-            id2 == ID2,
-            "Invalid gzip magic byte 2: expected {:#x}, got {:#x}",
-            ID2,
-            id2
-        );
+        ensure!(id2 == ID2, DecodeError::InvalidMagic);
 
         let cm = self.read_byte()?;
-        ensure!(
-            cm == COMPRESSION_METHOD,
-            "Unsupported compression method: {}",
-            cm
-        );
+        ensure!(cm == COMPRESSION_METHOD, DecodeError::UnsupportedMethod);
 
         let flags = self.read_byte()?;
 
@@ -101,8 +195,12 @@ impl<'a> Decoder<'a> {
         }
 
         if flags & FHCRC != 0 {
-            // TODO: CRC16
-            let _crc16 = self.read_bytes(2)?;
+            let header_crc16 = (crc32(&self.input_stream[..self.pos]) & 0xFFFF) as u16;
+            let crc16_bytes = self.read_bytes(2)?;
+            let stored_crc16 = u16::from_le_bytes([crc16_bytes[0], crc16_bytes[1]]);
+            if self.verify {
+                ensure!(header_crc16 == stored_crc16, DecodeError::ChecksumMismatch);
+            }
         }
 
         // NOTE: rest of the stream is compressed data, CRC32, and ISIZE
@@ -115,7 +213,46 @@ impl<'a> Decoder<'a> {
 
         let mut bitstream = BitStream::new(&self.input_stream[self.pos..]);
 
-        infalte(&mut bitstream)
+        let decoded = infalte(&mut bitstream)?;
+
+        bitstream.discard();
+        let trailer = bitstream.get_bytes(8)?;
+        if self.verify {
+            let stored_crc32 = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+            let stored_isize = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+
+            ensure!(stored_crc32 == crc32(&decoded), DecodeError::ChecksumMismatch);
+            ensure!(stored_isize == decoded.len() as u32, DecodeError::ChecksumMismatch);
+        }
+
+        // Advance past the whole member (header, payload, and trailer) so a
+        // subsequent member in a concatenated stream can be located.
+        self.pos += bitstream.byte_pos;
+
+        Ok(decoded)
+    }
+
+    /// Decodes a stream of one or more concatenated gzip members (as
+    /// produced by `gzip -c a b > ab.gz` or typical log-rotation tooling),
+    /// returning the concatenation of their decompressed payloads along
+    /// with each member's `Header`, in order.
+    pub fn decode_all(&mut self) -> Result<(Vec<u8>, Vec<Header>)> {
+        let mut output = self.decode()?;
+        let mut headers = vec![core::mem::take(&mut self.header)];
+
+        while self.pos + 2 <= self.input_stream.len()
+            && self.input_stream[self.pos] == ID1
+            && self.input_stream[self.pos + 1] == ID2
+        {
+            let mut member = Decoder::new(&self.input_stream[self.pos..]);
+            member.verify = self.verify;
+
+            output.extend_from_slice(&member.decode()?);
+            self.pos += member.pos;
+            headers.push(member.header);
+        }
+
+        Ok((output, headers))
     }
 
     fn read_byte(&mut self) -> Result<u8> {
@@ -126,7 +263,7 @@ impl<'a> Decoder<'a> {
     fn read_bytes(&mut self, count: usize) -> Result<&[u8]> {
         ensure!(
             self.pos + count <= self.input_stream.len(),
-            "Unexpected EOF"
+            DecodeError::UnexpectedEof
         );
         let bytes = &self.input_stream[self.pos..self.pos + count];
         self.pos += count;
@@ -142,14 +279,14 @@ impl<'a> Decoder<'a> {
             }
             bytes.push(byte);
         }
-        String::from_utf8(bytes).map_err(|e| anyhow::anyhow!("Invalid UTF-8 in string: {}", e))
+        String::from_utf8(bytes).map_err(|_| DecodeError::InvalidUtf8)
     }
 }
 
 // Most of the following code is adapted from madler/zlib
 // Available at: https://github.com/madler/zlib/blob/master/contrib/puff/puff.c
 
-fn infalte(bitstream: &mut BitStream) -> Result<Vec<u8>> {
+pub(crate) fn infalte(bitstream: &mut BitStream) -> Result<Vec<u8>> {
     let mut output = Vec::new();
 
     loop {
@@ -160,7 +297,7 @@ fn infalte(bitstream: &mut BitStream) -> Result<Vec<u8>> {
             0 => uncompressed(bitstream, &mut output)?,
             1 => huff_fixed(bitstream, &mut output)?,
             2 => huff_dynamic(bitstream, &mut output)?,
-            _ => bail!("reserved block type"),
+            _ => bail!(DecodeError::ReservedBlockType),
         };
 
         if is_final {
@@ -181,7 +318,7 @@ fn uncompressed(bitstream: &mut BitStream, output: &mut Vec<u8>) -> Result<()> {
     let len = u16::from_le_bytes([len[0], len[1]]);
     let com = u16::from_le_bytes([com[0], com[1]]);
 
-    ensure!(com == !len, "one's complement verification failed");
+    ensure!(com == !len, DecodeError::StoredBlockLengthMismatch);
 
     let data = bitstream.get_bytes(len as usize)?;
     output.extend_from_slice(data);
@@ -189,20 +326,14 @@ fn uncompressed(bitstream: &mut BitStream, output: &mut Vec<u8>) -> Result<()> {
     Ok(())
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 struct Huffman {
     symbols: Vec<u32>,
     count: Vec<u32>,
 }
 
 fn huff_fixed(bitstream: &mut BitStream, output: &mut Vec<u8>) -> Result<()> {
-    let mut lengths: [u32; 288] = [0; 288];
-    lengths[..=143].fill(8);
-    lengths[144..=255].fill(9);
-    lengths[256..=279].fill(7);
-    lengths[280..=287].fill(8);
-
-    let distances: [u32; 30] = [5; 30];
+    let (lengths, distances) = fixed_huffman_lengths();
 
     let len_huff = huff_table(&lengths);
     let dist_huff = huff_table(&distances);
@@ -271,39 +402,22 @@ fn codes(
     len_huff: &Huffman,
     dist_huff: &Huffman,
 ) -> Result<()> {
-    let lens = [
-        /* Size base for length codes 257..285 */
-        3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
-        131, 163, 195, 227, 258,
-    ];
-    let lext = [
-        /* Extra bits for length codes 257..285 */
-        0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
-    ];
-    let dists = [
-        /* Offset base for distance codes 0..29 */
-        1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
-        2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
-    ];
-    let dext = [
-        /* Extra bits for distance codes 0..29 */
-        0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12,
-        13, 13,
-    ];
-
     loop {
         let symbol = decode(bitstream, &len_huff)?;
         match symbol {
             0..256 => {
                 output.push(symbol as u8);
             }
-            257..285 => {
+            257..=285 => {
                 let symbol = symbol - 257;
-                ensure!(symbol < 29, "invalid symbol len");
-                let mut len = lens[symbol as usize] + bitstream.read(lext[symbol as usize])?;
+                ensure!(symbol < 29, DecodeError::BadHuffman);
+                let mut len = LENGTH_BASE[symbol as usize]
+                    + bitstream.read(LENGTH_EXTRA[symbol as usize])?;
                 let symbol = decode(bitstream, &dist_huff)?;
-                let dist = dists[symbol as usize] + bitstream.read(dext[symbol as usize])?;
-                ensure!((dist as usize) < output.len(), "invalid len symbol");
+                let dist =
+                    DIST_BASE[symbol as usize] + bitstream.read(DIST_EXTRA[symbol as usize])?;
+                // dist == output.len() is valid: it references the very first decoded byte.
+                ensure!((dist as usize) <= output.len(), DecodeError::BadHuffman);
                 while len > 0 {
                     let literal = output[output.len() - (dist as usize)];
                     output.push(literal);
@@ -345,7 +459,7 @@ fn huff_table(code_lengths: &[u32]) -> Huffman {
     huff
 }
 
-fn decode(bitstream: &mut BitStream, huff: &Huffman) -> Result<u32, anyhow::Error> {
+fn decode(bitstream: &mut BitStream, huff: &Huffman) -> Result<u32> {
     let mut code: u32 = 0;
     let mut first: u32 = 0;
     let mut index: u32 = 0;
@@ -368,10 +482,10 @@ fn decode(bitstream: &mut BitStream, huff: &Huffman) -> Result<u32, anyhow::Erro
         code <<= 1;
     }
 
-    Err(anyhow::anyhow!("unable to decode"))
+    Err(DecodeError::BadHuffman)
 }
 
-struct BitStream<'a> {
+pub(crate) struct BitStream<'a> {
     bytes: &'a [u8],
     byte_pos: usize,
     buf: u32,
@@ -379,7 +493,7 @@ struct BitStream<'a> {
 }
 
 impl<'a> BitStream<'a> {
-    fn new(bytes: &'a [u8]) -> Self {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
         Self {
             bytes,
             byte_pos: 0,
@@ -393,12 +507,7 @@ impl<'a> BitStream<'a> {
 
         while self.bits_in_buf < need {
             if self.byte_pos == self.bytes.len() {
-                bail!(
-                    "Unexpected EOF: total bytes - {}, byte_pos - {}, need - {}",
-                    self.bytes.len(),
-                    self.byte_pos,
-                    need
-                )
+                bail!(DecodeError::UnexpectedEof)
             }
             val |= (self.bytes[self.byte_pos] as u32) << self.bits_in_buf;
             self.byte_pos += 1;
@@ -411,9 +520,11 @@ impl<'a> BitStream<'a> {
         Ok(val & ((1 << need) - 1))
     }
 
-    fn get_bytes(&mut self, need: usize) -> Result<&[u8]> {
-        if self.byte_pos + need >= self.bytes.len() {
-            return Err(anyhow::anyhow!("Unexpected EOF"));
+    pub(crate) fn get_bytes(&mut self, need: usize) -> Result<&[u8]> {
+        // `>`, not `>=`: consuming exactly to the end of `bytes` is valid,
+        // e.g. reading the trailer of a stream with no further data after it.
+        if self.byte_pos + need > self.bytes.len() {
+            return Err(DecodeError::UnexpectedEof);
         }
 
         let s = &self.bytes[self.byte_pos..self.byte_pos + need];
@@ -422,8 +533,860 @@ impl<'a> BitStream<'a> {
         Ok(s)
     }
 
+    pub(crate) fn discard(&mut self) {
+        self.buf = 0;
+        self.bits_in_buf = 0;
+    }
+}
+
+// Streaming inflate
+//
+// `Inflate` lets the DEFLATE block loop above be driven incrementally: feed
+// it whatever bytes are on hand via `decompress`, and it reports whether it
+// needs more input, made output progress, or finished the final block. It
+// never reads past the bytes it has actually been given.
+//
+// Rather than hand-writing a field-by-field resumable state machine for
+// every bit read, each unit of work (a block header, a stored-block length
+// pair, a dynamic Huffman table, or one literal/match token) is attempted
+// against a checkpoint of the bit position; if the input runs out partway
+// through, the checkpoint is restored and `NeedMoreInput` is reported, so
+// the same unit of work is retried from scratch once more bytes arrive.
+// Units of work are bounded (a dynamic table header, the largest, is at most
+// a few thousand bits), so a retry costs no more than re-reading those
+// already-buffered bits.
+
+/// Outcome of one [`Inflate::decompress`] call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// The buffered input was exhausted before a full unit of work (a
+    /// header, table, or token) could be decoded; call again with more
+    /// input appended.
+    NeedMoreInput,
+    /// Output was appended to the caller's buffer; more may follow.
+    Progress,
+    /// The final block was reached; decoding is complete.
+    Done,
+}
+
+/// Why a resumable read inside a unit of work didn't produce a value.
+enum Need {
+    /// Ran out of buffered bits; roll back and wait for more input.
+    MoreInput,
+    /// The bits decoded don't describe a valid DEFLATE stream.
+    Invalid(DecodeError),
+}
+
+/// Result alias for reads inside a unit of work, which fail with [`Need`]
+/// rather than [`DecodeError`] so the caller can tell "roll back and wait
+/// for more input" apart from a genuine decode error.
+type NeedResult<T> = core::result::Result<T, Need>;
+
+#[derive(Clone, Copy)]
+struct Checkpoint {
+    byte_pos: usize,
+    buf: u32,
+    bits_in_buf: u32,
+}
+
+/// A `BitStream` over an input that arrives in chunks over time, with
+/// reads that can be checkpointed and rolled back.
+struct StreamBits {
+    bytes: Vec<u8>,
+    byte_pos: usize,
+    buf: u32,
+    bits_in_buf: u32,
+}
+
+impl StreamBits {
+    fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            byte_pos: 0,
+            buf: 0,
+            bits_in_buf: 0,
+        }
+    }
+
+    fn feed(&mut self, input: &[u8]) {
+        if self.byte_pos > 0 {
+            self.bytes.drain(..self.byte_pos);
+            self.byte_pos = 0;
+        }
+        self.bytes.extend_from_slice(input);
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            byte_pos: self.byte_pos,
+            buf: self.buf,
+            bits_in_buf: self.bits_in_buf,
+        }
+    }
+
+    fn restore(&mut self, cp: Checkpoint) {
+        self.byte_pos = cp.byte_pos;
+        self.buf = cp.buf;
+        self.bits_in_buf = cp.bits_in_buf;
+    }
+
+    fn read(&mut self, need: u32) -> NeedResult<u32> {
+        let mut val = self.buf;
+        let mut bits_in_buf = self.bits_in_buf;
+        let mut byte_pos = self.byte_pos;
+
+        while bits_in_buf < need {
+            if byte_pos == self.bytes.len() {
+                return Err(Need::MoreInput);
+            }
+            val |= (self.bytes[byte_pos] as u32) << bits_in_buf;
+            byte_pos += 1;
+            bits_in_buf += 8;
+        }
+
+        self.byte_pos = byte_pos;
+        self.buf = val >> need;
+        self.bits_in_buf = bits_in_buf - need;
+
+        Ok(val & ((1 << need) - 1))
+    }
+
     fn discard(&mut self) {
         self.buf = 0;
         self.bits_in_buf = 0;
     }
+
+    /// Requires exactly `need` buffered bytes, or fails without consuming any.
+    fn read_bytes_exact(&mut self, need: usize) -> NeedResult<Vec<u8>> {
+        debug_assert_eq!(self.bits_in_buf, 0);
+        if self.byte_pos + need > self.bytes.len() {
+            return Err(Need::MoreInput);
+        }
+        let out = self.bytes[self.byte_pos..self.byte_pos + need].to_vec();
+        self.byte_pos += need;
+        Ok(out)
+    }
+
+    /// Takes up to `max` currently-buffered bytes; may return fewer (or
+    /// none), unlike `read_bytes_exact`.
+    fn take_bytes(&mut self, max: usize) -> Vec<u8> {
+        debug_assert_eq!(self.bits_in_buf, 0);
+        let available = (self.bytes.len() - self.byte_pos).min(max);
+        let out = self.bytes[self.byte_pos..self.byte_pos + available].to_vec();
+        self.byte_pos += available;
+        out
+    }
+}
+
+fn decode_symbol(bits: &mut StreamBits, huff: &Huffman) -> NeedResult<u32> {
+    let mut code: u32 = 0;
+    let mut first: u32 = 0;
+    let mut index: u32 = 0;
+
+    for len in 1..=15 {
+        code |= bits.read(1)?;
+
+        let count = huff.count[len];
+        if code - first < count {
+            return Ok(huff.symbols[(index + (code - first)) as usize]);
+        }
+
+        index += count;
+        first += count;
+        first <<= 1;
+        code <<= 1;
+    }
+
+    Err(Need::Invalid(DecodeError::BadHuffman))
+}
+
+/// Parses the dynamic Huffman table header (RFC 1951 3.2.7), atomically:
+/// either the whole header decodes, or none of it is consumed.
+fn read_dynamic_tables(bits: &mut StreamBits) -> NeedResult<(Huffman, Huffman)> {
+    let order: [u16; 19] = [
+        16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+    ];
+
+    let mut lengths: [u32; 19] = [0; 19];
+
+    let hlit = bits.read(5)? + 257;
+    let hdist = bits.read(5)? + 1;
+    let hclen = bits.read(4)? + 4;
+
+    for i in 0..hclen {
+        lengths[order[i as usize] as usize] = bits.read(3)?;
+    }
+    for i in hclen..19 {
+        lengths[order[i as usize] as usize] = 0;
+    }
+
+    let lencode_huff = huff_table(&lengths);
+    let mut lengths: [u32; 316] = [0; 316];
+    let mut index: u32 = 0;
+
+    while index < hlit + hdist {
+        let mut symbol = decode_symbol(bits, &lencode_huff)?;
+        if symbol < 16 {
+            lengths[index as usize] = symbol;
+            index += 1;
+        } else {
+            let mut len = 0;
+            match symbol {
+                16 => {
+                    len = lengths[(index - 1) as usize];
+                    symbol = 3 + bits.read(2)?;
+                }
+                17 => {
+                    symbol = 3 + bits.read(3)?;
+                }
+                _ => {
+                    symbol = 11 + bits.read(7)?;
+                }
+            }
+            while symbol != 0 {
+                lengths[index as usize] = len;
+                symbol -= 1;
+                index += 1;
+            }
+        }
+    }
+
+    let len_huff = huff_table(&lengths[..hlit as usize]);
+    let dist_huff = huff_table(&lengths[(hlit as usize)..]);
+
+    Ok((len_huff, dist_huff))
+}
+
+enum TokenOutcome {
+    Continue,
+    EndOfBlock,
+}
+
+const WINDOW_MASK: usize = WINDOW_SIZE - 1;
+
+/// Fixed-capacity ring buffer holding only the most recently decoded 32 KB.
+/// Back-references never reach further than that, so resolving them (and
+/// copying overlapping matches, where `dist < len`) doesn't require keeping
+/// the whole decompressed output resident — only this bounded window plus
+/// whatever the caller's sink chooses to retain.
+struct Window {
+    ring: Box<[u8; WINDOW_SIZE]>,
+    total: usize,
+}
+
+impl Window {
+    fn new() -> Self {
+        Self {
+            ring: Box::new([0; WINDOW_SIZE]),
+            total: 0,
+        }
+    }
+
+    /// Total bytes ever pushed; used to validate that a distance doesn't
+    /// reach further back than what's actually been produced.
+    fn len(&self) -> usize {
+        self.total
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.ring[self.total & WINDOW_MASK] = byte;
+        self.total += 1;
+    }
+
+    /// The byte `dist` positions before the next one to be pushed.
+    fn byte_at_distance(&self, dist: usize) -> u8 {
+        self.ring[(self.total - dist) & WINDOW_MASK]
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Step {
+    BlockStart,
+    StoredHeader,
+    StoredBody { remaining: usize },
+    DynamicHeader,
+    Token,
+    Done,
+}
+
+/// Incremental, never-overreading DEFLATE decompressor: feed it input
+/// chunks of any size via [`Inflate::decompress`] and it decodes from the
+/// buffered input, picking up exactly where it left off on the next call. Unlike
+/// [`infalte`], it doesn't require the whole compressed stream up front and
+/// can be driven from a source larger than memory. Back-references are
+/// resolved from a bounded 32 KB [`Window`] rather than `output` itself, so
+/// a caller that drains `output` between calls keeps peak memory bounded
+/// regardless of total stream size; callers who'd rather just keep the
+/// full result can let `output` grow, same as the non-streaming `decode`.
+pub struct Inflate {
+    bits: StreamBits,
+    step: Step,
+    final_block: bool,
+    len_huff: Huffman,
+    dist_huff: Huffman,
+    window: Window,
+}
+
+impl Inflate {
+    pub fn new() -> Self {
+        Self {
+            bits: StreamBits::new(),
+            step: Step::BlockStart,
+            final_block: false,
+            len_huff: Huffman::default(),
+            dist_huff: Huffman::default(),
+            window: Window::new(),
+        }
+    }
+
+    /// Feeds `input` in and resumes decoding, appending decoded bytes to
+    /// `output`. Each call returns [`Status::Progress`] after at most one
+    /// literal/match token or one run of stored bytes, so call again (with
+    /// an empty `input` if there is nothing new) on `Progress`, with the
+    /// next chunk on [`Status::NeedMoreInput`]; stop once [`Status::Done`]
+    /// is returned.
+    pub fn decompress(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<Status> {
+        self.bits.feed(input);
+
+        loop {
+            match self.step {
+                Step::Done => return Ok(Status::Done),
+
+                Step::BlockStart => {
+                    let cp = self.bits.checkpoint();
+                    match self.read_block_header() {
+                        Ok(()) => {}
+                        Err(Need::MoreInput) => {
+                            self.bits.restore(cp);
+                            return Ok(Status::NeedMoreInput);
+                        }
+                        Err(Need::Invalid(err)) => bail!(err),
+                    }
+                }
+
+                Step::StoredHeader => {
+                    let cp = self.bits.checkpoint();
+                    self.bits.discard();
+                    match self.bits.read_bytes_exact(4) {
+                        Ok(lencom) => {
+                            let len = u16::from_le_bytes([lencom[0], lencom[1]]);
+                            let com = u16::from_le_bytes([lencom[2], lencom[3]]);
+                            ensure!(com == !len, DecodeError::StoredBlockLengthMismatch);
+                            self.step = Step::StoredBody {
+                                remaining: len as usize,
+                            };
+                        }
+                        Err(Need::MoreInput) => {
+                            self.bits.restore(cp);
+                            return Ok(Status::NeedMoreInput);
+                        }
+                        Err(Need::Invalid(err)) => bail!(err),
+                    }
+                }
+
+                Step::StoredBody { remaining } => {
+                    if remaining == 0 {
+                        self.step = self.next_block_step();
+                        continue;
+                    }
+                    let chunk = self.bits.take_bytes(remaining);
+                    if chunk.is_empty() {
+                        return Ok(Status::NeedMoreInput);
+                    }
+                    for &byte in &chunk {
+                        self.window.push(byte);
+                    }
+                    output.extend_from_slice(&chunk);
+                    self.step = Step::StoredBody {
+                        remaining: remaining - chunk.len(),
+                    };
+                    return Ok(Status::Progress);
+                }
+
+                Step::DynamicHeader => {
+                    let cp = self.bits.checkpoint();
+                    match read_dynamic_tables(&mut self.bits) {
+                        Ok((len_huff, dist_huff)) => {
+                            self.len_huff = len_huff;
+                            self.dist_huff = dist_huff;
+                            self.step = Step::Token;
+                        }
+                        Err(Need::MoreInput) => {
+                            self.bits.restore(cp);
+                            return Ok(Status::NeedMoreInput);
+                        }
+                        Err(Need::Invalid(err)) => bail!(err),
+                    }
+                }
+
+                Step::Token => {
+                    let cp = self.bits.checkpoint();
+                    match self.decode_token(output) {
+                        Ok(TokenOutcome::Continue) => return Ok(Status::Progress),
+                        Ok(TokenOutcome::EndOfBlock) => self.step = self.next_block_step(),
+                        Err(Need::MoreInput) => {
+                            self.bits.restore(cp);
+                            return Ok(Status::NeedMoreInput);
+                        }
+                        Err(Need::Invalid(err)) => bail!(err),
+                    }
+                }
+            }
+        }
+    }
+
+    fn next_block_step(&self) -> Step {
+        if self.final_block {
+            Step::Done
+        } else {
+            Step::BlockStart
+        }
+    }
+
+    fn read_block_header(&mut self) -> NeedResult<()> {
+        let is_final = self.bits.read(1)?;
+        let block_type = self.bits.read(2)?;
+
+        self.final_block = is_final == 1;
+
+        self.step = match block_type {
+            0 => Step::StoredHeader,
+            1 => {
+                let (lengths, distances) = fixed_huffman_lengths();
+                self.len_huff = huff_table(&lengths);
+                self.dist_huff = huff_table(&distances);
+                Step::Token
+            }
+            2 => Step::DynamicHeader,
+            _ => return Err(Need::Invalid(DecodeError::ReservedBlockType)),
+        };
+
+        Ok(())
+    }
+
+    /// Decodes one literal or length/distance match, copying any match
+    /// bytes into the sliding window and `output`. Nothing is written
+    /// until every read involved has succeeded, so a `Need::MoreInput`
+    /// partway through leaves both untouched.
+    fn decode_token(&mut self, output: &mut Vec<u8>) -> NeedResult<TokenOutcome> {
+        let symbol = decode_symbol(&mut self.bits, &self.len_huff)?;
+
+        match symbol {
+            0..256 => {
+                let byte = symbol as u8;
+                self.window.push(byte);
+                output.push(byte);
+                Ok(TokenOutcome::Continue)
+            }
+            256 => Ok(TokenOutcome::EndOfBlock),
+            257..=285 => {
+                let idx = (symbol - 257) as usize;
+                if idx >= 29 {
+                    return Err(Need::Invalid(DecodeError::BadHuffman));
+                }
+                let len = LENGTH_BASE[idx] + self.bits.read(LENGTH_EXTRA[idx])?;
+
+                let dsym = decode_symbol(&mut self.bits, &self.dist_huff)? as usize;
+                if dsym >= 30 {
+                    return Err(Need::Invalid(DecodeError::BadHuffman));
+                }
+                let dist = DIST_BASE[dsym] + self.bits.read(DIST_EXTRA[dsym])?;
+                if dist as usize > self.window.len() {
+                    return Err(Need::Invalid(DecodeError::BadHuffman));
+                }
+
+                for _ in 0..len {
+                    let literal = self.window.byte_at_distance(dist as usize);
+                    self.window.push(literal);
+                    output.push(literal);
+                }
+
+                Ok(TokenOutcome::Continue)
+            }
+            _ => Err(Need::Invalid(DecodeError::BadHuffman)),
+        }
+    }
+}
+
+impl Default for Inflate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Encoder
+//
+// Produces a valid gzip member: the header, a DEFLATE-compressed payload,
+// and the CRC32/ISIZE trailer. Compression is LZ77 over a 32 KB window
+// (hash-chain match finding with lazy matching) encoded with the fixed
+// Huffman tables from `fixed_huffman_lengths`/`codes` above, falling back
+// to stored blocks when that doesn't actually shrink the input.
+
+const WINDOW_SIZE: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+const HASH_SIZE: usize = 1 << HASH_BITS;
+const MAX_CHAIN: usize = 128;
+
+#[derive(Debug, Clone, Copy)]
+enum Token {
+    Literal(u8),
+    Match { len: u16, dist: u16 },
+}
+
+/// Hash-chain index over 3-byte sequences: `head[hash]` is the most recent
+/// position with that hash, `prev[pos]` chains back to the previous one.
+struct MatchFinder<'a> {
+    data: &'a [u8],
+    head: Vec<i32>,
+    prev: Vec<i32>,
+}
+
+impl<'a> MatchFinder<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            head: vec![-1; HASH_SIZE],
+            prev: vec![-1; data.len().max(1)],
+        }
+    }
+
+    fn hash(&self, pos: usize) -> usize {
+        let b = self.data;
+        let h = (b[pos] as u32) << 16 | (b[pos + 1] as u32) << 8 | b[pos + 2] as u32;
+        ((h.wrapping_mul(0x9E3779B1)) >> (32 - HASH_BITS)) as usize
+    }
+
+    fn insert(&mut self, pos: usize) {
+        if pos + MIN_MATCH > self.data.len() {
+            return;
+        }
+        let h = self.hash(pos);
+        self.prev[pos] = self.head[h];
+        self.head[h] = pos as i32;
+    }
+
+    /// Longest match at `pos` within the sliding window, if any.
+    fn find_match(&self, pos: usize) -> Option<(usize, usize)> {
+        if pos + MIN_MATCH > self.data.len() {
+            return None;
+        }
+
+        let max_len = (self.data.len() - pos).min(MAX_MATCH);
+        let limit = pos.saturating_sub(WINDOW_SIZE);
+
+        let mut candidate = self.head[self.hash(pos)];
+        let mut best = (0usize, 0usize);
+        let mut chain = 0;
+
+        while candidate >= 0 && candidate as usize >= limit && chain < MAX_CHAIN {
+            let cand = candidate as usize;
+            let len = match_length(self.data, cand, pos, max_len);
+            if len > best.0 {
+                best = (len, pos - cand);
+                if len >= max_len {
+                    break;
+                }
+            }
+            candidate = self.prev[cand];
+            chain += 1;
+        }
+
+        (best.0 >= MIN_MATCH).then_some(best)
+    }
+}
+
+fn match_length(data: &[u8], a: usize, b: usize, max_len: usize) -> usize {
+    let mut n = 0;
+    while n < max_len && data[a + n] == data[b + n] {
+        n += 1;
+    }
+    n
+}
+
+/// Greedy/lazy LZ77 tokenization: a match is taken unless the very next
+/// position yields a strictly longer one, in which case a literal is
+/// emitted and the better match is taken from there instead.
+fn lz77(data: &[u8]) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut finder = MatchFinder::new(data);
+    let mut i = 0;
+
+    while i < data.len() {
+        let found = finder.find_match(i);
+        finder.insert(i);
+
+        let Some((len, dist)) = found else {
+            tokens.push(Token::Literal(data[i]));
+            i += 1;
+            continue;
+        };
+
+        let better_next = i + 1 < data.len()
+            && finder
+                .find_match(i + 1)
+                .is_some_and(|(next_len, _)| next_len > len);
+
+        if better_next {
+            tokens.push(Token::Literal(data[i]));
+            i += 1;
+            continue;
+        }
+
+        tokens.push(Token::Match {
+            len: len as u16,
+            dist: dist as u16,
+        });
+        for p in i + 1..i + len {
+            finder.insert(p);
+        }
+        i += len;
+    }
+
+    tokens
+}
+
+fn length_to_symbol(len: u32) -> (usize, u32, u32) {
+    for i in (0..LENGTH_BASE.len()).rev() {
+        if len >= LENGTH_BASE[i] {
+            return (257 + i, len - LENGTH_BASE[i], LENGTH_EXTRA[i]);
+        }
+    }
+    unreachable!("length must be >= MIN_MATCH")
+}
+
+fn dist_to_symbol(dist: u32) -> (usize, u32, u32) {
+    for i in (0..DIST_BASE.len()).rev() {
+        if dist >= DIST_BASE[i] {
+            return (i, dist - DIST_BASE[i], DIST_EXTRA[i]);
+        }
+    }
+    unreachable!("distance must be >= 1")
+}
+
+/// Canonical Huffman codes for a set of RFC-1951-style bit lengths, built
+/// the same way `huff_table` reconstructs the decode side from lengths.
+fn huffman_codes(lengths: &[u32]) -> Vec<u32> {
+    let max_bits = lengths.iter().copied().max().unwrap_or(0) as usize;
+    let mut bl_count = vec![0u32; max_bits + 1];
+    for &len in lengths {
+        if len > 0 {
+            bl_count[len as usize] += 1;
+        }
+    }
+
+    let mut codes = vec![0u32; lengths.len()];
+    let mut code = 0u32;
+    let mut next_code = vec![0u32; max_bits + 1];
+    for bits in 1..=max_bits {
+        code = (code + bl_count[bits - 1]) << 1;
+        next_code[bits] = code;
+    }
+
+    for (symbol, &len) in lengths.iter().enumerate() {
+        if len > 0 {
+            codes[symbol] = next_code[len as usize];
+            next_code[len as usize] += 1;
+        }
+    }
+
+    codes
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    buf: u32,
+    bits_in_buf: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            buf: 0,
+            bits_in_buf: 0,
+        }
+    }
+
+    /// Writes the `nbits` low bits of `val`, LSB first (as DEFLATE packs
+    /// everything except Huffman codes themselves).
+    fn write(&mut self, val: u32, nbits: u32) {
+        self.buf |= (val & ((1u32.wrapping_shl(nbits)).wrapping_sub(1))) << self.bits_in_buf;
+        self.bits_in_buf += nbits;
+        while self.bits_in_buf >= 8 {
+            self.bytes.push((self.buf & 0xFF) as u8);
+            self.buf >>= 8;
+            self.bits_in_buf -= 8;
+        }
+    }
+
+    /// Writes a Huffman code MSB first, matching how `decode` accumulates
+    /// bits into a code value one at a time.
+    fn write_huffman(&mut self, code: u32, len: u32) {
+        for shift in (0..len).rev() {
+            self.write((code >> shift) & 1, 1);
+        }
+    }
+
+    fn align_to_byte(&mut self) {
+        if self.bits_in_buf > 0 {
+            self.bytes.push((self.buf & 0xFF) as u8);
+            self.buf = 0;
+            self.bits_in_buf = 0;
+        }
+    }
+
+    fn write_bytes(&mut self, data: &[u8]) {
+        debug_assert_eq!(self.bits_in_buf, 0, "byte data must be byte-aligned");
+        self.bytes.extend_from_slice(data);
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+fn encode_fixed_block(tokens: &[Token], writer: &mut BitWriter, is_final: bool) {
+    let (len_lengths, dist_lengths) = fixed_huffman_lengths();
+    let len_codes = huffman_codes(&len_lengths);
+    let dist_codes = huffman_codes(&dist_lengths);
+
+    writer.write(is_final as u32, 1);
+    writer.write(1, 2); // block type 1: fixed Huffman codes
+
+    for token in tokens {
+        match *token {
+            Token::Literal(byte) => {
+                writer.write_huffman(len_codes[byte as usize], len_lengths[byte as usize]);
+            }
+            Token::Match { len, dist } => {
+                let (sym, extra, extra_bits) = length_to_symbol(len as u32);
+                writer.write_huffman(len_codes[sym], len_lengths[sym]);
+                writer.write(extra, extra_bits);
+
+                let (dsym, dextra, dextra_bits) = dist_to_symbol(dist as u32);
+                writer.write_huffman(dist_codes[dsym], dist_lengths[dsym]);
+                writer.write(dextra, dextra_bits);
+            }
+        }
+    }
+
+    writer.write_huffman(len_codes[256], len_lengths[256]); // end-of-block
+    writer.align_to_byte();
+}
+
+/// Stored (uncompressed) DEFLATE blocks, chunked to the 64K-1 byte limit.
+fn encode_stored_blocks(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    let max_chunk = u16::MAX as usize;
+    let mut offset = 0;
+
+    loop {
+        let chunk_len = (data.len() - offset).min(max_chunk);
+        let is_final = offset + chunk_len == data.len();
+
+        writer.write(is_final as u32, 1);
+        writer.write(0, 2); // block type 0: stored
+        writer.align_to_byte();
+        writer.write_bytes(&(chunk_len as u16).to_le_bytes());
+        writer.write_bytes(&(!(chunk_len as u16)).to_le_bytes());
+        writer.write_bytes(&data[offset..offset + chunk_len]);
+
+        offset += chunk_len;
+        if is_final {
+            break;
+        }
+    }
+
+    writer.into_bytes()
+}
+
+fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    for (n, slot) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 {
+                0xEDB88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+        }
+        *slot = c;
+    }
+    table
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let table = crc32_table();
+    let mut crc = 0xFFFFFFFFu32;
+    for &byte in data {
+        let index = ((crc ^ byte as u32) & 0xFF) as usize;
+        crc = table[index] ^ (crc >> 8);
+    }
+    crc ^ 0xFFFFFFFF
+}
+
+/// Encodes raw bytes plus a [`Header`] into a gzip member.
+pub struct Encoder<'a> {
+    data: &'a [u8],
+    header: Header,
+}
+
+impl<'a> Encoder<'a> {
+    pub fn new(data: &'a [u8], header: Header) -> Self {
+        Self { data, header }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write_header(&mut out);
+        out.extend_from_slice(&self.compress());
+        out.extend_from_slice(&crc32(self.data).to_le_bytes());
+        out.extend_from_slice(&(self.data.len() as u32).to_le_bytes());
+        out
+    }
+
+    fn write_header(&self, out: &mut Vec<u8>) {
+        out.push(ID1);
+        out.push(ID2);
+        out.push(COMPRESSION_METHOD);
+
+        let mut flags = 0u8;
+        if !self.header.name.is_empty() {
+            flags |= FNAME;
+        }
+        if !self.header.comment.is_empty() {
+            flags |= FCOMMENT;
+        }
+        out.push(flags);
+
+        out.extend_from_slice(&self.header.modtime.to_le_bytes());
+        out.push(0); // XFL: no compression-level hint
+        out.push(self.header.os);
+
+        if !self.header.name.is_empty() {
+            out.extend_from_slice(self.header.name.as_bytes());
+            out.push(0);
+        }
+        if !self.header.comment.is_empty() {
+            out.extend_from_slice(self.header.comment.as_bytes());
+            out.push(0);
+        }
+    }
+
+    fn compress(&self) -> Vec<u8> {
+        let tokens = lz77(self.data);
+
+        let mut writer = BitWriter::new();
+        encode_fixed_block(&tokens, &mut writer, true);
+        let compressed = writer.into_bytes();
+
+        if compressed.len() < self.data.len() {
+            compressed
+        } else {
+            encode_stored_blocks(self.data)
+        }
+    }
 }
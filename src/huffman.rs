@@ -1,186 +1,310 @@
-use std::cmp::Ordering;
-use std::collections::{BTreeMap, BinaryHeap, HashMap};
-use std::rc::Rc;
-
-#[derive(Debug, PartialEq, Eq)]
-enum NodeType {
-    Leaf(char),
-    Internal(Rc<Node>, Rc<Node>),
-}
+use alloc::collections::BTreeMap;
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec, vec::Vec};
+
+/// Codes longer than this are disallowed; kept identical to DEFLATE's own
+/// limit so the package-merge pass below never has to consider deeper trees.
+const DEFAULT_MAX_CODE_LEN: u32 = 15;
 
-#[derive(Debug)]
-struct Node {
-    node_type: NodeType,
-    freq: u32,
+/// A canonical Huffman codebook over `char` symbols.
+///
+/// Unlike a tree built straight from frequencies, a canonical code can be
+/// rebuilt from nothing but each symbol's bit length (sorted by length, then
+/// by symbol value, codes assigned sequentially) -- the same scheme
+/// `gzip`'s dynamic Huffman blocks use. That's what makes `lengths()` /
+/// `from_lengths()` a full round trip: the codebook is just data, no tree
+/// has to survive alongside it.
+#[derive(Debug, Clone, Default)]
+pub struct Huffman {
+    /// Symbols in ascending order, aligned with `lengths` and `codes`.
+    symbols: Vec<char>,
+    lengths: Vec<u32>,
+    codes: Vec<u32>,
 }
 
-impl Node {
-    fn new_leaf(symbol: char, freq: u32) -> Self {
-        Node {
-            node_type: NodeType::Leaf(symbol),
-            freq,
-        }
+impl Huffman {
+    /// Builds a codebook from `input`'s symbol frequencies, limiting codes
+    /// to `DEFAULT_MAX_CODE_LEN` bits.
+    pub fn from(input: &str) -> Self {
+        Self::from_max_len(input, DEFAULT_MAX_CODE_LEN)
     }
 
-    fn new_internal(left: Rc<Node>, right: Rc<Node>) -> Self {
-        Node {
-            node_type: NodeType::Internal(left.clone(), right.clone()),
-            freq: left.freq + right.freq,
+    /// Like `from`, but with a caller-chosen max code length.
+    pub fn from_max_len(input: &str, max_len: u32) -> Self {
+        let mut freq: BTreeMap<char, u32> = BTreeMap::new();
+        for symbol in input.chars() {
+            *freq.entry(symbol).or_default() += 1;
         }
-    }
-}
 
-impl Eq for Node {}
+        let symbols: Vec<char> = freq.keys().copied().collect();
+        let weights: Vec<u32> = freq.values().copied().collect();
+        let lengths = package_merge(&weights, max_len);
+        let codes = canonical_codes(&lengths);
 
-impl PartialEq for Node {
-    fn eq(&self, other: &Self) -> bool {
-        self.freq == other.freq
+        Self {
+            symbols,
+            lengths,
+            codes,
+        }
     }
-}
 
-impl Ord for Node {
-    fn cmp(&self, other: &Self) -> Ordering {
-        other.freq.cmp(&self.freq)
-    }
-}
+    /// Rebuilds a codebook from a serialized `(symbol, length)` list, as
+    /// produced by `lengths()` -- no frequency table required.
+    pub fn from_lengths(lengths: &[(char, u32)]) -> Self {
+        let mut pairs: Vec<(char, u32)> = lengths.to_vec();
+        pairs.sort_by_key(|&(symbol, _)| symbol);
+
+        let symbols: Vec<char> = pairs.iter().map(|&(symbol, _)| symbol).collect();
+        let lengths: Vec<u32> = pairs.iter().map(|&(_, len)| len).collect();
+        let codes = canonical_codes(&lengths);
 
-impl PartialOrd for Node {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+        Self {
+            symbols,
+            lengths,
+            codes,
+        }
     }
-}
 
-pub struct Huffman<'a> {
-    root: Option<Rc<Node>>,
-    codec_dict: HashMap<char, String>,
-    input: &'a str,
-}
+    /// The codebook as `(symbol, length)` pairs, in canonical (ascending
+    /// symbol) order. Serialize this alongside `encode`'s output so the
+    /// other side can reconstruct the codebook via `from_lengths`.
+    pub fn lengths(&self) -> Vec<(char, u32)> {
+        self.symbols
+            .iter()
+            .copied()
+            .zip(self.lengths.iter().copied())
+            .collect()
+    }
 
-impl<'a> Huffman<'a> {
-    pub fn from(input: &'a str) -> Self {
-        let mut freq_dict: BTreeMap<char, u32> = BTreeMap::new();
+    /// Encodes `input` with this codebook, returning the packed bits and
+    /// the number of meaningful bits (the last byte may be zero-padded).
+    pub fn encode(&self, input: &str) -> (Vec<u8>, usize) {
+        let mut writer = BitWriter::new();
         for symbol in input.chars() {
-            *freq_dict.entry(symbol).or_default() += 1;
+            let index = self
+                .symbols
+                .binary_search(&symbol)
+                .expect("symbol not present in this codebook");
+            writer.write_huffman(self.codes[index], self.lengths[index]);
         }
 
-        let mut pqueue = BinaryHeap::new();
-        for (&symbol, &freq) in freq_dict.iter() {
-            pqueue.push(Rc::new(Node::new_leaf(symbol, freq)));
-        }
+        let bit_len = writer.bit_len();
+        (writer.into_bytes(), bit_len)
+    }
 
-        while pqueue.len() > 1 {
-            let ln = pqueue.pop().expect("checked with while loop condition");
-            let rn = pqueue.pop().expect("checked with while loop condition");
+    /// Decodes a bitstream produced by `encode` on this same codebook.
+    pub fn decode(&self, bytes: &[u8], bit_len: usize) -> String {
+        let (count, order) = canonical_order(&self.lengths);
+        let max_bits = self.lengths.iter().copied().max().unwrap_or(0);
+        let mut reader = BitReader::new(bytes, bit_len);
+        let mut decoded = String::new();
 
-            let internal = Rc::new(Node::new_internal(ln, rn));
-            pqueue.push(internal);
-        }
+        while reader.has_more() {
+            let mut code = 0u32;
+            let mut first = 0u32;
+            let mut index = 0u32;
 
-        let root = pqueue.pop();
-        let mut codec_dict = HashMap::new();
-        match root {
-            Some(root) => {
-                fill(&root, String::new(), &mut codec_dict);
-                Self {
-                    input,
-                    codec_dict,
-                    root: Some(root),
+            for len in 1..=max_bits {
+                code = (code << 1) | reader.read_bit();
+                let n = count[len as usize];
+                if code - first < n {
+                    let symbol_index = order[(index + (code - first)) as usize];
+                    decoded.push(self.symbols[symbol_index]);
+                    break;
                 }
+                index += n;
+                first = (first + n) << 1;
             }
-            None => Self {
-                input,
-                root: None,
-                codec_dict: codec_dict,
-            },
         }
+
+        decoded
     }
+}
 
-    pub fn encode(&self) -> String {
-        let mut encoded = String::new();
-        for symbol in self.input.chars() {
-            if let Some(code) = self.codec_dict.get(&symbol) {
-                encoded += code;
-            }
+/// Length-limited code lengths via the package-merge algorithm: each symbol
+/// starts as a "coin" of weight equal to its frequency. At each denomination
+/// level, from the deepest (`max_len`) up to the shallowest, the previous
+/// level's packages are paired up and merged (by weight) with the original
+/// coins; the `2*(n-1)` cheapest items out of the final level, counted by
+/// how often each symbol appears in them, give each symbol its code length.
+fn package_merge(weights: &[u32], max_len: u32) -> Vec<u32> {
+    let n = weights.len();
+    if n == 0 {
+        return Vec::new();
+    }
+    if n == 1 {
+        // A single symbol still needs a real (1-bit) code to be emitted.
+        return vec![1];
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by_key(|&i| weights[i]);
+
+    let leaves: Vec<(u64, Vec<usize>)> = order
+        .iter()
+        .map(|&i| (weights[i] as u64, vec![i]))
+        .collect();
+
+    let mut packages: Vec<(u64, Vec<usize>)> = Vec::new();
+    for _ in 0..max_len {
+        let mut paired = Vec::new();
+        let mut i = 0;
+        while i + 1 < packages.len() {
+            let (w1, s1) = &packages[i];
+            let (w2, s2) = &packages[i + 1];
+            let mut symbols = s1.clone();
+            symbols.extend_from_slice(s2);
+            paired.push((w1 + w2, symbols));
+            i += 2;
         }
 
-        encoded
+        let mut merged = leaves.clone();
+        merged.extend(paired);
+        merged.sort_by_key(|&(weight, _)| weight);
+        packages = merged;
     }
 
-    pub fn decode(&self, encoded: &str) -> String {
-        let mut decoded = String::new();
+    let mut lengths = vec![0u32; n];
+    let take = 2 * (n - 1);
+    for (_, symbols) in packages.iter().take(take) {
+        for &index in symbols {
+            lengths[index] += 1;
+        }
+    }
+
+    lengths
+}
+
+/// Assigns canonical code values from per-symbol bit lengths: symbols are
+/// implicitly ordered by `(length, symbol index)` since `lengths` is already
+/// in ascending symbol-index order, so walking lengths 1..=max and handing
+/// out consecutive codes reproduces exactly that ordering.
+fn canonical_codes(lengths: &[u32]) -> Vec<u32> {
+    let max_bits = lengths.iter().copied().max().unwrap_or(0) as usize;
 
-        // Handle edge cases
-        if encoded.is_empty() {
-            return decoded;
+    let mut bl_count = vec![0u32; max_bits + 1];
+    for &len in lengths {
+        if len > 0 {
+            bl_count[len as usize] += 1;
         }
+    }
 
-        let Some(root) = &self.root else {
-            return decoded; // No tree means no decoding possible
-        };
+    let mut next_code = vec![0u32; max_bits + 1];
+    let mut code = 0u32;
+    for bits in 1..=max_bits {
+        code = (code + bl_count[bits - 1]) << 1;
+        next_code[bits] = code;
+    }
 
-        // Special case: single character tree
-        if let NodeType::Leaf(symbol) = &root.node_type {
-            // For single character, each bit represents one occurrence
-            for _ in encoded.chars() {
-                decoded.push(*symbol);
-            }
-            return decoded;
+    let mut codes = vec![0u32; lengths.len()];
+    for (symbol, &len) in lengths.iter().enumerate() {
+        if len > 0 {
+            codes[symbol] = next_code[len as usize];
+            next_code[len as usize] += 1;
         }
+    }
 
-        let mut current_node = root;
+    codes
+}
 
-        for bit_char in encoded.chars() {
-            match bit_char {
-                '0' => {
-                    if let NodeType::Internal(left, _) = &current_node.node_type {
-                        current_node = left;
-                    } else {
-                        // This shouldn't happen with valid encoding
-                        break;
-                    }
-                }
-                '1' => {
-                    if let NodeType::Internal(_, right) = &current_node.node_type {
-                        current_node = right;
-                    } else {
-                        // This shouldn't happen with valid encoding
-                        break;
-                    }
-                }
-                _ => unreachable!("encoded format must be binary"),
-            }
+/// Builds the `(count, order)` tables a canonical bit-by-bit decode needs:
+/// `count[len]` is how many symbols have that code length, and `order` lists
+/// symbol indices grouped by length (then by symbol index within a length),
+/// matching the order `canonical_codes` handed codes out in.
+fn canonical_order(lengths: &[u32]) -> (Vec<u32>, Vec<usize>) {
+    let max_bits = lengths.iter().copied().max().unwrap_or(0) as usize;
 
-            if let NodeType::Leaf(symbol) = &current_node.node_type {
-                decoded.push(*symbol);
-                current_node = root;
-            }
+    let mut count = vec![0u32; max_bits + 1];
+    for &len in lengths {
+        if len > 0 {
+            count[len as usize] += 1;
         }
+    }
 
-        decoded
+    let mut next = vec![0u32; max_bits + 1];
+    for bits in 1..max_bits {
+        next[bits + 1] = next[bits] + count[bits];
+    }
+
+    let mut order = vec![0usize; lengths.iter().filter(|&&len| len > 0).count()];
+    for (symbol, &len) in lengths.iter().enumerate() {
+        if len > 0 {
+            order[next[len as usize] as usize] = symbol;
+            next[len as usize] += 1;
+        }
     }
+
+    (count, order)
+}
+
+/// Packs bits MSB-first per Huffman code into a byte stream, LSB-first
+/// within each byte -- the same convention `gzip`'s bit writer uses.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buf: u8,
+    bits_in_buf: u32,
 }
 
-fn fill(node: &Node, code: String, dict: &mut HashMap<char, String>) {
-    match &node.node_type {
-        NodeType::Leaf(symbol) => {
-            let _ = dict.insert(*symbol, code);
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            buf: 0,
+            bits_in_buf: 0,
         }
-        NodeType::Internal(ln, rn) => {
-            fill(ln, format!("{}0", code), dict);
-            fill(rn, format!("{}1", code), dict);
+    }
+
+    fn write_bit(&mut self, bit: u32) {
+        self.buf |= ((bit & 1) as u8) << self.bits_in_buf;
+        self.bits_in_buf += 1;
+        if self.bits_in_buf == 8 {
+            self.bytes.push(self.buf);
+            self.buf = 0;
+            self.bits_in_buf = 0;
         }
     }
-}
 
-#[allow(dead_code)]
-fn print_tree(node: &Node, code: String) {
-    match &node.node_type {
-        NodeType::Leaf(symbol) => {
-            println!("Sybmol: '{}': {} (freq: {})", symbol, code, node.freq)
+    fn write_huffman(&mut self, code: u32, len: u32) {
+        for shift in (0..len).rev() {
+            self.write_bit((code >> shift) & 1);
         }
-        NodeType::Internal(ln, rn) => {
-            print_tree(ln, format!("{}0", code));
-            print_tree(rn, format!("{}1", code));
+    }
+
+    fn bit_len(&self) -> usize {
+        self.bytes.len() * 8 + self.bits_in_buf as usize
+    }
+
+    fn into_bytes(mut self) -> Vec<u8> {
+        if self.bits_in_buf > 0 {
+            self.bytes.push(self.buf);
         }
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    bit_pos: usize,
+    bit_len: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8], bit_len: usize) -> Self {
+        Self {
+            bytes,
+            bit_pos: 0,
+            bit_len,
+        }
+    }
+
+    fn has_more(&self) -> bool {
+        self.bit_pos < self.bit_len
+    }
+
+    fn read_bit(&mut self) -> u32 {
+        let byte = self.bytes[self.bit_pos / 8];
+        let bit = (byte >> (self.bit_pos % 8)) & 1;
+        self.bit_pos += 1;
+        bit as u32
     }
 }
@@ -0,0 +1,122 @@
+// RFC 1950 zlib container: a 2-byte header (plus an optional 4-byte preset
+// dictionary id) wrapping a raw DEFLATE stream, trailed by an Adler-32
+// checksum. Many ecosystem formats (PNG IDAT chunks among them) use this
+// instead of gzip, so this is a thin sibling of `gzip::Decoder` that reuses
+// the same `infalte`/`BitStream` machinery for the payload.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::gzip::{BitStream, DecodeError, Result, infalte};
+
+const CM_DEFLATE: u8 = 8;
+const FDICT: u8 = 1 << 5;
+
+#[derive(Debug, Default)]
+pub struct Header {
+    /// LZ77 window size declared by CINFO, in bytes (`1 << (CINFO + 8)`).
+    pub window_size: usize,
+    /// Adler-32 of the preset dictionary, present when FDICT is set.
+    pub dict_id: Option<u32>,
+}
+
+#[derive(Debug)]
+pub struct Decoder<'a> {
+    pub header: Header,
+    pub pos: usize,
+    pub input_stream: &'a [u8],
+    /// Whether to check the Adler-32 trailer against the decoded bytes.
+    /// On by default.
+    pub verify: bool,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Self {
+            header: Header::default(),
+            input_stream: input,
+            pos: 0,
+            verify: true,
+        }
+    }
+
+    pub fn parse_header(&mut self) -> Result<()> {
+        if self.pos != 0 {
+            return Err(DecodeError::HeaderAlreadyParsed);
+        }
+
+        if self.input_stream.len() < 2 {
+            return Err(DecodeError::UnexpectedEof);
+        }
+
+        let cmf = self.input_stream[0];
+        let flg = self.input_stream[1];
+
+        if cmf & 0x0F != CM_DEFLATE {
+            return Err(DecodeError::UnsupportedMethod);
+        }
+
+        let check = (cmf as u16) * 256 + flg as u16;
+        if !check.is_multiple_of(31) {
+            return Err(DecodeError::InvalidMagic);
+        }
+
+        let cinfo = cmf >> 4;
+        self.header.window_size = 1usize << (cinfo as u32 + 8);
+        self.pos = 2;
+
+        if flg & FDICT != 0 {
+            let dict_bytes = self.read_bytes(4)?;
+            self.header.dict_id = Some(u32::from_be_bytes([
+                dict_bytes[0],
+                dict_bytes[1],
+                dict_bytes[2],
+                dict_bytes[3],
+            ]));
+        }
+
+        Ok(())
+    }
+
+    pub fn decode(&mut self) -> Result<Vec<u8>> {
+        self.parse_header()?;
+
+        let mut bitstream = BitStream::new(&self.input_stream[self.pos..]);
+        let decoded = infalte(&mut bitstream)?;
+
+        if self.verify {
+            bitstream.discard();
+            let trailer = bitstream.get_bytes(4)?;
+            let stored = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+            if stored != adler32(&decoded) {
+                return Err(DecodeError::ChecksumMismatch);
+            }
+        }
+
+        Ok(decoded)
+    }
+
+    fn read_bytes(&mut self, count: usize) -> Result<&[u8]> {
+        if self.pos + count > self.input_stream.len() {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        let bytes = &self.input_stream[self.pos..self.pos + count];
+        self.pos += count;
+        Ok(bytes)
+    }
+}
+
+/// Adler-32 (RFC 1950 2.5): two 16-bit sums modulo 65521, s1 starting at 1.
+fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+
+    let mut s1 = 1u32;
+    let mut s2 = 0u32;
+
+    for &byte in data {
+        s1 = (s1 + byte as u32) % MOD_ADLER;
+        s2 = (s2 + s1) % MOD_ADLER;
+    }
+
+    (s2 << 16) | s1
+}